@@ -113,8 +113,8 @@
 //! type and then returns to client code.
 //!
 
+use super::handlemap::{HandleError, HandleMap};
 use super::RustBuffer;
-use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// ForeignCallback is the Rust representation of a foreign language function.
 /// It is the basis for all callbacks interfaces. It is registered exactly once per callback interface,
@@ -125,13 +125,66 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 /// The `method` selector specifies the method that will be called on the object, by looking it up in a list of methods from
 /// the IDL. The index is 1 indexed. Note that the list of methods is generated by at uniffi from the IDL and used in all
 /// bindings: so we can rely on the method list being stable within the same run of uniffi.
-pub type ForeignCallback =
-    unsafe extern "C" fn(handle: u64, method: u32, args: RustBuffer) -> RustBuffer;
+///
+/// Rather than returning a value directly, the foreign side reports its outcome through a status
+/// code (the `i32` return) and writes any payload into `out_buf`:
+///
+/// * [`CALLBACK_SUCCESS`] — the method returned normally; its lowered return value is in `out_buf`
+///   (to be lifted via the return type's `try_read`).
+/// * [`CALLBACK_ERROR`] — the method raised one of its declared `[Throws=MyError]` errors; the
+///   serialized error variant is in `out_buf` (to be lifted via the error type's `try_read`).
+/// * [`CALLBACK_UNEXPECTED_ERROR`] — the foreign implementation threw something undeclared or
+///   panicked; `out_buf` holds a UTF-8 message describing what went wrong.
+pub type ForeignCallback = unsafe extern "C" fn(
+    handle: u64,
+    method: u32,
+    args: RustBuffer,
+    out_buf: *mut RustBuffer,
+) -> i32;
 
 /// The method index used by the Drop trait to communicate to the foreign language side that Rust has finished with it,
 /// and it can be deleted from the handle map.
 pub const IDX_CALLBACK_FREE: u32 = 0;
 
+/// Status code: the foreign method returned normally, with its lowered value in `out_buf`.
+pub const CALLBACK_SUCCESS: i32 = 0;
+
+/// Status code: the foreign method raised one of its declared errors, serialized into `out_buf`.
+pub const CALLBACK_ERROR: i32 = 1;
+
+/// Status code: the foreign method threw something undeclared or panicked; `out_buf` holds a
+/// UTF-8 message.
+pub const CALLBACK_UNEXPECTED_ERROR: i32 = 2;
+
+/// An error the callback machinery raises when the foreign side misbehaves — it threw something it
+/// didn't declare, panicked, or was reached through a stale handle.
+///
+/// This is never one of the interface's declared errors. For a `[Throws=E]` method it is routed
+/// into `Err(E)` via `E: From<UnexpectedUniFFICallbackError>` (the derive for a uniffi error type
+/// generates that conversion), so it reaches Rust through the normal `Result` channel rather than
+/// unwinding across the FFI.
+#[derive(Debug)]
+pub struct UnexpectedUniFFICallbackError {
+    /// A human-readable description of what went wrong on the foreign side.
+    pub reason: String,
+}
+
+impl UnexpectedUniFFICallbackError {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for UnexpectedUniFFICallbackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unexpected callback error: {}", self.reason)
+    }
+}
+
+impl std::error::Error for UnexpectedUniFFICallbackError {}
+
 // Overly-paranoid sanity checking to ensure that these types are
 // convertible between each-other. `transmute` actually should check this for
 // us too, but this helps document the invariants we rely on in this code.
@@ -142,43 +195,115 @@ pub const IDX_CALLBACK_FREE: u32 = 0;
 static_assertions::assert_eq_size!(usize, ForeignCallback);
 static_assertions::assert_eq_size!(usize, Option<ForeignCallback>);
 
-/// Struct to hold a foreign callback.
+/// Holds the foreign callback vtables registered for a callback interface.
+///
+/// Instead of a single one-shot global pointer, vtables live in a [`HandleMap`]: each call to
+/// [`register`](ForeignCallbackInternals::register) returns a fresh handle, so re-registering (a
+/// library reload, a second isolated instance, a swapped test double) cleanly adds another entry
+/// rather than panicking. `IDX_CALLBACK_FREE` releases an entry by calling
+/// [`unregister`](ForeignCallbackInternals::unregister), and a stale handle resolves to an error
+/// through the status-code channel rather than dereferencing freed memory.
 pub struct ForeignCallbackInternals {
-    callback_ptr: AtomicUsize,
+    callbacks: HandleMap<ForeignCallback>,
 }
 
-const EMPTY_PTR: usize = 0;
-
 impl ForeignCallbackInternals {
     pub const fn new() -> Self {
         ForeignCallbackInternals {
-            callback_ptr: AtomicUsize::new(EMPTY_PTR),
+            callbacks: HandleMap::new(),
         }
     }
 
-    pub fn set_callback(&self, callback: ForeignCallback) {
-        let as_usize = callback as usize;
-        let old_ptr = self.callback_ptr.compare_exchange(
-            EMPTY_PTR,
-            as_usize,
-            Ordering::SeqCst,
-            Ordering::SeqCst,
-        );
-        match old_ptr {
-            // We get the previous value back. If this is anything except EMPTY_PTR,
-            // then this has been set before we get here.
-            Ok(EMPTY_PTR) => (),
-            _ =>
-            // This is an internal bug, the other side of the FFI should ensure
-            // it sets this only once.
-            {
-                panic!("Bug: call set_callback multiple times. This is likely a uniffi bug")
+    /// Register a foreign callback vtable, returning the handle the foreign side uses to refer to
+    /// it. Calling this more than once adds distinct entries instead of panicking.
+    pub fn register(&self, callback: ForeignCallback) -> u64 {
+        self.callbacks.insert(callback)
+    }
+
+    /// Release the vtable behind `callback_handle` (on `IDX_CALLBACK_FREE`). A stale or
+    /// already-freed handle is a no-op.
+    pub fn unregister(&self, callback_handle: u64) {
+        let _ = self.callbacks.remove(callback_handle);
+    }
+
+    /// Invoke a registered callback and turn its status-code/out-buffer outcome into a `Result`.
+    ///
+    /// Generated proxy methods that implement a `[Throws=E]` callback-interface trait call this.
+    /// `callback_handle` identifies the vtable and `handle` the foreign object. On
+    /// [`CALLBACK_SUCCESS`] the `out_buf` is handed to `extract_success` to lift the `Ok` value, on
+    /// [`CALLBACK_ERROR`] it is handed to `extract_error` to lift the declared `Err(E)`, and on
+    /// [`CALLBACK_UNEXPECTED_ERROR`], an unknown status code, or a stale `callback_handle`, an
+    /// [`UnexpectedUniFFICallbackError`] is built and converted into `Err(E)`. Everything reaches
+    /// Rust through the `Result` channel — no path aborts the process or unwinds across the FFI.
+    pub fn invoke_callback<T, E>(
+        &self,
+        callback_handle: u64,
+        handle: u64,
+        method: u32,
+        args: RustBuffer,
+        extract_success: impl FnOnce(RustBuffer) -> T,
+        extract_error: impl FnOnce(RustBuffer) -> E,
+    ) -> Result<T, E>
+    where
+        E: From<UnexpectedUniFFICallbackError>,
+    {
+        let callback = match self.callbacks.get(callback_handle, |cb| *cb) {
+            Ok(callback) => callback,
+            // The vtable has been unregistered (or never existed): surface it through the error
+            // channel rather than dereferencing freed memory.
+            Err(HandleError::StaleHandle) => {
+                return Err(UnexpectedUniFFICallbackError::new(format!(
+                    "callback invoked with a stale handle {callback_handle}"
+                ))
+                .into())
             }
         };
+        let mut out_buf = RustBuffer::new();
+        let status = unsafe { callback(handle, method, args, &mut out_buf) };
+        match status {
+            CALLBACK_SUCCESS => Ok(extract_success(out_buf)),
+            CALLBACK_ERROR => Err(extract_error(out_buf)),
+            CALLBACK_UNEXPECTED_ERROR => {
+                // The foreign side threw something we didn't declare, or panicked. `out_buf` holds
+                // a UTF-8 description; surface it rather than silently dropping it.
+                let message = String::from_utf8_lossy(&out_buf.destroy_into_vec()).into_owned();
+                Err(UnexpectedUniFFICallbackError::new(message).into())
+            }
+            _ => Err(UnexpectedUniFFICallbackError::new(format!(
+                "callback returned an unknown status code {status}"
+            ))
+            .into()),
+        }
     }
 
-    pub fn get_callback(&self) -> Option<ForeignCallback> {
-        let ptr_value = self.callback_ptr.load(Ordering::SeqCst);
-        unsafe { std::mem::transmute::<usize, Option<ForeignCallback>>(ptr_value) }
+    /// Invoke a callback for a method that is *not* declared to throw, returning the lifted value.
+    ///
+    /// There is no error channel for such methods, so any non-success outcome — a declared error
+    /// that shouldn't exist, an unexpected foreign error, or a stale handle — is a contract
+    /// violation. We panic with a descriptive message naming the method rather than unwrapping an
+    /// opaque payload.
+    pub fn invoke_callback_infallible<T>(
+        &self,
+        callback_handle: u64,
+        handle: u64,
+        method: u32,
+        method_name: &str,
+        args: RustBuffer,
+        extract_success: impl FnOnce(RustBuffer) -> T,
+    ) -> T {
+        let result = self.invoke_callback::<T, UnexpectedUniFFICallbackError>(
+            callback_handle,
+            handle,
+            method,
+            args,
+            extract_success,
+            |buf| {
+                let message = String::from_utf8_lossy(&buf.destroy_into_vec()).into_owned();
+                UnexpectedUniFFICallbackError::new(message)
+            },
+        );
+        result.unwrap_or_else(|err| {
+            panic!("undeclared error from non-throwing callback method {method_name}: {err}")
+        })
     }
 }
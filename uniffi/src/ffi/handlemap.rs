@@ -0,0 +1,141 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A concurrent handle map for objects that live on one side of the FFI and are referred to from
+//! the other by an opaque `u64` handle.
+//!
+//! Callback interfaces used to keep a single process-global pointer (see
+//! [`ForeignCallbackInternals`]), which could never be reset: registering a second callback
+//! hard-panicked. That rules out legitimate scenarios — reloading a dynamic library in a
+//! long-lived host, running two isolated instances of the generated bindings, or hot-swapping a
+//! test double. A [`HandleMap`] fixes this by handing out a fresh handle per registration and
+//! detecting stale handles instead of dereferencing freed memory.
+//!
+//! # Handle layout
+//!
+//! A handle packs a slot index and a generation counter into its `u64`:
+//!
+//! ```text
+//! 63            32 31             0
+//! +--------------+----------------+
+//! |  generation  |      index     |
+//! +--------------+----------------+
+//! ```
+//!
+//! Every time a slot is reused its generation is bumped, so a handle issued against an earlier
+//! occupant no longer matches and [`HandleMap::get`]/[`HandleMap::remove`] report
+//! [`HandleError::StaleHandle`] rather than returning the new occupant (a use-after-free).
+//!
+//! Generations start at 1, never 0, so that the very first handle (slot 0, generation 1) is never
+//! the all-zero value used as the null/invalid-handle sentinel across the FFI.
+//!
+//! [`ForeignCallbackInternals`]: crate::ffi::foreigncallbacks::ForeignCallbackInternals
+
+use std::sync::Mutex;
+
+/// Errors that can arise when resolving a handle against a [`HandleMap`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum HandleError {
+    /// The handle's generation didn't match the slot's, or the slot is empty — the entry it
+    /// referred to has been removed. Callers should surface this through the status-code channel
+    /// rather than treating it as a valid entry.
+    StaleHandle,
+}
+
+const INDEX_MASK: u64 = 0x0000_0000_ffff_ffff;
+const GENERATION_SHIFT: u64 = 32;
+
+struct Slot<T> {
+    // The current generation of this slot. A handle only resolves if its generation matches.
+    generation: u32,
+    value: Option<T>,
+}
+
+/// A slab of slots, each addressable by a generation-tagged handle, safe under concurrent access.
+pub struct HandleMap<T> {
+    slots: Mutex<Vec<Slot<T>>>,
+}
+
+impl<T> HandleMap<T> {
+    /// Create an empty map.
+    pub const fn new() -> Self {
+        HandleMap {
+            slots: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Insert a value, returning a fresh handle that refers to it.
+    pub fn insert(&self, value: T) -> u64 {
+        let mut slots = self.slots.lock().unwrap();
+        // Reuse the first free slot if there is one, otherwise grow.
+        match slots.iter().position(|slot| slot.value.is_none()) {
+            Some(index) => {
+                let slot = &mut slots[index];
+                slot.value = Some(value);
+                pack_handle(index, slot.generation)
+            }
+            None => {
+                let index = slots.len();
+                // Generations start at 1 so no live handle is ever the all-zero FFI null sentinel.
+                slots.push(Slot {
+                    generation: 1,
+                    value: Some(value),
+                });
+                pack_handle(index, 1)
+            }
+        }
+    }
+
+    /// Run `f` against the value behind `handle`, returning its result.
+    ///
+    /// Returns [`HandleError::StaleHandle`] if the handle no longer refers to a live entry.
+    pub fn get<R>(&self, handle: u64, f: impl FnOnce(&T) -> R) -> Result<R, HandleError> {
+        let slots = self.slots.lock().unwrap();
+        let (index, generation) = unpack_handle(handle);
+        match slots.get(index) {
+            Some(slot) if slot.generation == generation => match &slot.value {
+                Some(value) => Ok(f(value)),
+                None => Err(HandleError::StaleHandle),
+            },
+            _ => Err(HandleError::StaleHandle),
+        }
+    }
+
+    /// Remove and return the value behind `handle`, bumping the slot's generation so the handle
+    /// can never resolve again.
+    ///
+    /// Returns [`HandleError::StaleHandle`] if the handle was already removed or never valid.
+    pub fn remove(&self, handle: u64) -> Result<T, HandleError> {
+        let mut slots = self.slots.lock().unwrap();
+        let (index, generation) = unpack_handle(handle);
+        match slots.get_mut(index) {
+            Some(slot) if slot.generation == generation && slot.value.is_some() => {
+                // Bump the generation so any outstanding copy of this handle is now stale,
+                // skipping 0 on wraparound so a reused slot never mints the null sentinel.
+                slot.generation = match slot.generation.wrapping_add(1) {
+                    0 => 1,
+                    next => next,
+                };
+                Ok(slot.value.take().unwrap())
+            }
+            _ => Err(HandleError::StaleHandle),
+        }
+    }
+}
+
+impl<T> Default for HandleMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn pack_handle(index: usize, generation: u32) -> u64 {
+    ((generation as u64) << GENERATION_SHIFT) | (index as u64 & INDEX_MASK)
+}
+
+fn unpack_handle(handle: u64) -> (usize, u32) {
+    let index = (handle & INDEX_MASK) as usize;
+    let generation = (handle >> GENERATION_SHIFT) as u32;
+    (index, generation)
+}
@@ -0,0 +1,13 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! FFI support types shared by all of uniffi's generated scaffolding.
+
+pub mod foreigncallbacks;
+pub mod handlemap;
+pub mod rustfuture;
+
+pub use foreigncallbacks::*;
+pub use handlemap::*;
+pub use rustfuture::*;
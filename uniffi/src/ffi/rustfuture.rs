@@ -0,0 +1,261 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! [`RustFuture`] is the Rust representation of an exported `async fn`.
+//!
+//! # Scope
+//!
+//! This module is the *runtime* half of async support: the [`RustFuture`] type, its poll/complete/
+//! free operations, and the [`ffi_rust_future_scaffolding!`](crate::ffi_rust_future_scaffolding)
+//! macro that emits the three C ABI entry points for one async method. The bindings-generation
+//! side that expands the macro per exported `async fn` and lowers/lifts future results in each
+//! foreign backend is not part of this module; until it lands, async methods are not wired
+//! end-to-end through codegen.
+//!
+//! # How it works.
+//!
+//! When the foreign language calls an exported async method, Rust does *not* run the method to
+//! completion. Instead it constructs the `Future`, boxes it up into a [`RustFuture`], and hands a
+//! handle (a raw pointer) straight back across the FFI. Nothing has been polled yet.
+//!
+//! The foreign language then drives the future from its own event loop by calling three generated
+//! C ABI entry points, one set per async method:
+//!
+//! * `..._rust_future_poll(handle, waker, waker_data)` polls the future once. `waker` is a foreign
+//!   function pointer — the analog of [`ForeignCallback`] for callback interfaces — that Rust
+//!   stores and invokes (with `waker_data`) whenever the future becomes ready to make progress
+//!   again. Poll returns [`RustFuturePoll::Ready`] or [`RustFuturePoll::Pending`]; on `Pending` the
+//!   foreign side simply waits for its `waker` to fire and then polls again.
+//! * `..._rust_future_complete(handle, out_status) -> RustBuffer` is called once poll reported
+//!   `Ready`. It extracts the serialized return value (or error) from the completed future, writing
+//!   it into a [`RustBuffer`] exactly as [`RustBufferFfiConverter::write`] would for a synchronous
+//!   return, and reports success/failure through `out_status`.
+//! * `..._rust_future_free(handle)` drops the `RustFuture`. It is safe to call exactly once, after
+//!   completion (or to abandon a future that will never be polled to completion).
+//!
+//! ## Threading
+//!
+//! The future is wrapped in a `Mutex` so that it is only ever polled by one thread at a time, even
+//! if the foreign event loop is multi-threaded. The stored result is likewise guarded, so that
+//! `complete` observes the value written by the final `poll` regardless of which thread ran it.
+//!
+//! [`ForeignCallback`]: crate::ffi::foreigncallbacks::ForeignCallback
+//! [`RustBufferFfiConverter::write`]: crate::RustBufferFfiConverter::write
+
+use super::RustBuffer;
+use crate::{FfiConverter, RustCallStatus};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+/// Result of polling a [`RustFuture`] across the FFI.
+///
+/// Kept ABI-stable as a plain `i8` so it can be returned directly from the generated
+/// `rust_future_poll` entry point.
+#[repr(i8)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum RustFuturePoll {
+    /// The future completed; the foreign side should now call `rust_future_complete`.
+    Ready = 0,
+    /// The future is not ready. The foreign side should wait for its waker to fire, then poll
+    /// again.
+    Pending = 1,
+}
+
+/// A foreign function that schedules another poll of the future.
+///
+/// This is the async analog of [`ForeignCallback`]. It is not registered up front in any holder:
+/// the foreign side passes it, together with `waker_data`, on every call to `rust_future_poll`,
+/// and it is invoked from the [`Waker`] that Rust passes to `Future::poll`: when the future wants
+/// to be polled again, waking it calls this with the opaque `waker_data` the foreign side handed us
+/// at poll time, so the foreign event loop can re-enter `rust_future_poll`.
+///
+/// [`ForeignCallback`]: crate::ffi::foreigncallbacks::ForeignCallback
+pub type RustFutureForeignWakerFunction = unsafe extern "C" fn(waker_data: *const ());
+
+/// The Rust representation of an exported `async fn`.
+///
+/// Boxes the future as a `Pin<Box<F>>` of its concrete type (not a `dyn Future` trait object),
+/// together with the small amount of state needed to drive it from
+/// the foreign side one poll at a time. On completion the `Output` is lowered through its
+/// [`FfiConverter`] exactly as a synchronous return would be. This subsystem covers the
+/// buffer-serialized return types (those whose `FfiConverter::FfiType` is [`RustBuffer`]); an
+/// `async fn` returning a type lowered to a primitive FFI type (e.g. `u32`, whose `FfiType` is
+/// `u32` rather than [`RustBuffer`]) does not satisfy the [`complete`](RustFuture::complete) bound
+/// and so is not covered by this entry-point set.
+pub struct RustFuture<F, T>
+where
+    F: Future<Output = T>,
+{
+    // `None` once the future has completed and its result has been stored.
+    future: Mutex<Option<Pin<Box<F>>>>,
+    result: Mutex<Option<T>>,
+}
+
+impl<F, T> RustFuture<F, T>
+where
+    F: Future<Output = T>,
+{
+    /// Box up a future, returning a raw handle to hand across the FFI.
+    ///
+    /// The returned pointer owns the future; it must eventually be released with
+    /// [`RustFuture::free`] and must not be used afterwards.
+    pub fn new(future: F) -> *mut Self {
+        Arc::into_raw(Arc::new(Self {
+            future: Mutex::new(Some(Box::pin(future))),
+            result: Mutex::new(None),
+        })) as *mut Self
+    }
+
+    /// Poll the future once, registering `waker` so the foreign side is told when to poll again.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must have come from [`RustFuture::new`] and not yet have been freed.
+    pub unsafe fn poll(
+        handle: *const Self,
+        waker: RustFutureForeignWakerFunction,
+        waker_data: *const (),
+    ) -> RustFuturePoll {
+        let this = raw_to_arc(handle);
+        // Guard the future so it is only ever polled by one thread at a time.
+        let mut future_slot = this.future.lock().unwrap();
+        let poll = match future_slot.as_mut() {
+            // Already completed (or freed of its future); nothing more to do.
+            None => return RustFuturePoll::Ready,
+            Some(future) => {
+                let rust_waker = build_waker(waker, waker_data);
+                future
+                    .as_mut()
+                    .poll(&mut Context::from_waker(&rust_waker))
+            }
+        };
+        match poll {
+            Poll::Ready(value) => {
+                *this.result.lock().unwrap() = Some(value);
+                // Drop the future now that it has produced its value.
+                *future_slot = None;
+                RustFuturePoll::Ready
+            }
+            Poll::Pending => RustFuturePoll::Pending,
+        }
+    }
+
+    /// Extract the serialized result of a completed future into a [`RustBuffer`].
+    ///
+    /// # Safety
+    ///
+    /// `handle` must have come from [`RustFuture::new`]; the future must have previously polled
+    /// [`RustFuturePoll::Ready`].
+    pub unsafe fn complete(handle: *const Self, out_status: &mut RustCallStatus) -> RustBuffer
+    where
+        T: FfiConverter<FfiType = RustBuffer>,
+    {
+        let this = raw_to_arc(handle);
+        let result = this
+            .result
+            .lock()
+            .unwrap()
+            .take()
+            .expect("RustFuture::complete called before the future was ready");
+        crate::call_with_output(out_status, || T::lower(result))
+    }
+
+    /// Drop the future. Safe to call exactly once per handle.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must have come from [`RustFuture::new`] and not have been freed already.
+    pub unsafe fn free(handle: *const Self) {
+        drop(Arc::from_raw(handle));
+    }
+}
+
+// Reconstruct a borrowed `Arc` from a raw handle without taking ownership of the refcount.
+unsafe fn raw_to_arc<F, T>(handle: *const RustFuture<F, T>) -> Arc<RustFuture<F, T>>
+where
+    F: Future<Output = T>,
+{
+    let arc = Arc::from_raw(handle);
+    // Bump the count back up: the caller still owns the handle.
+    let clone = Arc::clone(&arc);
+    std::mem::forget(arc);
+    clone
+}
+
+// Build a `Waker` that, when woken, invokes the stored foreign waker function so the foreign event
+// loop schedules another poll. We never need to clone or drop real state here: the foreign
+// `waker_data` is an opaque token owned by the foreign side and outlives the poll.
+fn build_waker(waker: RustFutureForeignWakerFunction, waker_data: *const ()) -> Waker {
+    // Pack the function pointer and its data into the `RawWaker` data slot via a boxed pair, so a
+    // wake from another thread still reaches the right foreign callback.
+    let boxed = Box::into_raw(Box::new((waker, waker_data))) as *const ();
+    unsafe { Waker::from_raw(RawWaker::new(boxed, &WAKER_VTABLE)) }
+}
+
+static WAKER_VTABLE: RawWakerVTable =
+    RawWakerVTable::new(waker_clone, waker_wake, waker_wake_by_ref, waker_drop);
+
+unsafe fn waker_clone(data: *const ()) -> RawWaker {
+    let (waker, waker_data) = *(data as *const (RustFutureForeignWakerFunction, *const ()));
+    let boxed = Box::into_raw(Box::new((waker, waker_data))) as *const ();
+    RawWaker::new(boxed, &WAKER_VTABLE)
+}
+
+unsafe fn waker_wake(data: *const ()) {
+    let boxed = Box::from_raw(data as *mut (RustFutureForeignWakerFunction, *const ()));
+    let (waker, waker_data) = *boxed;
+    waker(waker_data);
+}
+
+unsafe fn waker_wake_by_ref(data: *const ()) {
+    let (waker, waker_data) = *(data as *const (RustFutureForeignWakerFunction, *const ()));
+    waker(waker_data);
+}
+
+unsafe fn waker_drop(data: *const ()) {
+    drop(Box::from_raw(
+        data as *mut (RustFutureForeignWakerFunction, *const ()),
+    ));
+}
+
+/// Generate the three `#[no_mangle] extern "C"` entry points for one async method.
+///
+/// This is intended to be expanded by the scaffolding template once per exported `async fn`,
+/// passing the three FFI symbol names (`..._rust_future_poll`, `..._rust_future_complete`,
+/// `..._rust_future_free`) plus the future and its output type. Each entry point simply forwards
+/// to the matching [`RustFuture`] method, so the unsafe contract documented there governs the
+/// whole ABI. The template change that emits these calls is not part of this module yet (see the
+/// module-level *Scope* note).
+#[macro_export]
+macro_rules! ffi_rust_future_scaffolding {
+    ($poll:ident, $complete:ident, $free:ident, $future:ty, $output:ty) => {
+        #[no_mangle]
+        pub unsafe extern "C" fn $poll(
+            handle: *const $crate::ffi::rustfuture::RustFuture<$future, $output>,
+            waker: $crate::ffi::rustfuture::RustFutureForeignWakerFunction,
+            waker_data: *const (),
+        ) -> $crate::ffi::rustfuture::RustFuturePoll {
+            $crate::ffi::rustfuture::RustFuture::poll(handle, waker, waker_data)
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn $complete(
+            handle: *const $crate::ffi::rustfuture::RustFuture<$future, $output>,
+            out_status: &mut $crate::RustCallStatus,
+        ) -> $crate::RustBuffer {
+            $crate::ffi::rustfuture::RustFuture::complete(handle, out_status)
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn $free(
+            handle: *const $crate::ffi::rustfuture::RustFuture<$future, $output>,
+        ) {
+            $crate::ffi::rustfuture::RustFuture::free(handle)
+        }
+    };
+}
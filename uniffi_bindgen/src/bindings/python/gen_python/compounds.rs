@@ -0,0 +1,186 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::backend::{CodeOracle, CodeType, Literal, Type};
+use std::fmt;
+
+use super::primitives::render_literal;
+
+// The compound types lower/lift through a generated `FfiConverter...` helper, as the primitive
+// types do; its name is `FfiConverterOptional`, `FfiConverterSequence` or `FfiConverterMap`
+// followed by the `type_label` of the inner (or key and value) types. Their `literal` hooks delegate to the shared `render_literal` so that
+// a UDL default of `null`, `[]`, or `{}` becomes the idiomatic `None`, `[]`, or `{}` in a Python
+// keyword-argument default.
+
+/// `T?` — an optional value, rendered in Python as `typing.Optional[T]`.
+pub struct OptionalCodeType {
+    inner: Type,
+}
+
+impl OptionalCodeType {
+    pub fn new(inner: Type) -> Self {
+        Self { inner }
+    }
+
+    fn inner(&self) -> &Type {
+        &self.inner
+    }
+}
+
+impl CodeType for OptionalCodeType {
+    fn type_label(&self, oracle: &dyn CodeOracle) -> String {
+        format!(
+            "typing.Optional[{}]",
+            oracle.find(self.inner()).type_label(oracle)
+        )
+    }
+
+    fn literal(&self, oracle: &dyn CodeOracle, literal: &Literal) -> String {
+        render_literal(oracle, literal)
+    }
+
+    fn lower(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display) -> String {
+        format!(
+            "{}._lower({})",
+            self.ffi_converter_name(oracle),
+            oracle.var_name(nm)
+        )
+    }
+
+    fn write(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display, target: &dyn fmt::Display) -> String {
+        format!(
+            "{}._write({}, {})",
+            self.ffi_converter_name(oracle),
+            oracle.var_name(nm),
+            target
+        )
+    }
+
+    fn lift(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display) -> String {
+        format!("{}._lift({})", self.ffi_converter_name(oracle), nm)
+    }
+
+    fn read(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display) -> String {
+        format!("{}._read({})", self.ffi_converter_name(oracle), nm)
+    }
+}
+
+impl OptionalCodeType {
+    fn ffi_converter_name(&self, oracle: &dyn CodeOracle) -> String {
+        format!("FfiConverterOptional{}", oracle.find(self.inner()).type_label(oracle))
+    }
+}
+
+/// `sequence<T>` — rendered in Python as `typing.List[T]`.
+pub struct SequenceCodeType {
+    inner: Type,
+}
+
+impl SequenceCodeType {
+    pub fn new(inner: Type) -> Self {
+        Self { inner }
+    }
+
+    fn inner(&self) -> &Type {
+        &self.inner
+    }
+
+    fn ffi_converter_name(&self, oracle: &dyn CodeOracle) -> String {
+        format!("FfiConverterSequence{}", oracle.find(self.inner()).type_label(oracle))
+    }
+}
+
+impl CodeType for SequenceCodeType {
+    fn type_label(&self, oracle: &dyn CodeOracle) -> String {
+        format!("typing.List[{}]", oracle.find(self.inner()).type_label(oracle))
+    }
+
+    fn literal(&self, oracle: &dyn CodeOracle, literal: &Literal) -> String {
+        render_literal(oracle, literal)
+    }
+
+    fn lower(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display) -> String {
+        format!(
+            "{}._lower({})",
+            self.ffi_converter_name(oracle),
+            oracle.var_name(nm)
+        )
+    }
+
+    fn write(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display, target: &dyn fmt::Display) -> String {
+        format!(
+            "{}._write({}, {})",
+            self.ffi_converter_name(oracle),
+            oracle.var_name(nm),
+            target
+        )
+    }
+
+    fn lift(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display) -> String {
+        format!("{}._lift({})", self.ffi_converter_name(oracle), nm)
+    }
+
+    fn read(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display) -> String {
+        format!("{}._read({})", self.ffi_converter_name(oracle), nm)
+    }
+}
+
+/// `record<K, V>` — rendered in Python as `dict[K, V]`.
+pub struct MapCodeType {
+    key: Type,
+    value: Type,
+}
+
+impl MapCodeType {
+    pub fn new(key: Type, value: Type) -> Self {
+        Self { key, value }
+    }
+
+    fn ffi_converter_name(&self, oracle: &dyn CodeOracle) -> String {
+        format!(
+            "FfiConverterMap{}{}",
+            oracle.find(&self.key).type_label(oracle),
+            oracle.find(&self.value).type_label(oracle)
+        )
+    }
+}
+
+impl CodeType for MapCodeType {
+    fn type_label(&self, oracle: &dyn CodeOracle) -> String {
+        format!(
+            "dict[{}, {}]",
+            oracle.find(&self.key).type_label(oracle),
+            oracle.find(&self.value).type_label(oracle)
+        )
+    }
+
+    fn literal(&self, oracle: &dyn CodeOracle, literal: &Literal) -> String {
+        render_literal(oracle, literal)
+    }
+
+    fn lower(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display) -> String {
+        format!(
+            "{}._lower({})",
+            self.ffi_converter_name(oracle),
+            oracle.var_name(nm)
+        )
+    }
+
+    fn write(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display, target: &dyn fmt::Display) -> String {
+        format!(
+            "{}._write({}, {})",
+            self.ffi_converter_name(oracle),
+            oracle.var_name(nm),
+            target
+        )
+    }
+
+    fn lift(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display) -> String {
+        format!("{}._lift({})", self.ffi_converter_name(oracle), nm)
+    }
+
+    fn read(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display) -> String {
+        format!("{}._read({})", self.ffi_converter_name(oracle), nm)
+    }
+}
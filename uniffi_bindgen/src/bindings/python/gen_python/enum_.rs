@@ -0,0 +1,58 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::backend::{CodeOracle, CodeType, Literal};
+use std::fmt;
+
+use super::primitives::render_literal;
+
+/// An `enum` declared in the UDL, exposed in Python as a class of the same name.
+///
+/// Its `literal` hook delegates to the shared [`render_literal`], which renders an enum-variant
+/// default as `ClassName.VARIANT` so a `dictionary` field defaulting to a variant carries that
+/// value as a Python keyword-argument default.
+pub struct EnumCodeType {
+    id: String,
+}
+
+impl EnumCodeType {
+    pub fn new(id: String) -> Self {
+        Self { id }
+    }
+}
+
+impl CodeType for EnumCodeType {
+    fn type_label(&self, oracle: &dyn CodeOracle) -> String {
+        oracle.class_name(&self.id)
+    }
+
+    fn literal(&self, oracle: &dyn CodeOracle, literal: &Literal) -> String {
+        render_literal(oracle, literal)
+    }
+
+    fn lower(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display) -> String {
+        format!(
+            "FfiConverter{}._lower({})",
+            self.type_label(oracle),
+            oracle.var_name(nm)
+        )
+    }
+
+    fn write(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display, target: &dyn fmt::Display) -> String {
+        format!(
+            "FfiConverter{}._write({}, {})",
+            self.type_label(oracle),
+            oracle.var_name(nm),
+            target
+        )
+    }
+
+    fn lift(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display) -> String {
+        format!("FfiConverter{}._lift({})", self.type_label(oracle), nm)
+    }
+
+    fn read(&self, oracle: &dyn CodeOracle, nm: &dyn fmt::Display) -> String {
+        format!("FfiConverter{}._read({})", self.type_label(oracle), nm)
+    }
+}
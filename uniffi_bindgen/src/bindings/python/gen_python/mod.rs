@@ -0,0 +1,9 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! `CodeType` implementations for the Python backend.
+
+pub(super) mod compounds;
+pub(super) mod enum_;
+pub(super) mod primitives;
@@ -11,7 +11,12 @@ use std::fmt;
 #[allow(unused_imports)]
 use super::filters;
 
-fn render_literal(_oracle: &dyn CodeOracle, literal: &Literal) -> String {
+/// Render a UDL literal as the Python source for that value.
+///
+/// Shared with the compound and enum `CodeType`s (see [`super::compounds`] and [`super::enum_`]),
+/// whose `literal` hooks delegate here so that a `dictionary` field defaulting to `null`, `[]`,
+/// `{}`, or an enum variant renders the same idiomatic Python everywhere.
+pub(super) fn render_literal(oracle: &dyn CodeOracle, literal: &Literal) -> String {
     match literal {
         Literal::Boolean(v) => {
             if *v {
@@ -34,6 +39,18 @@ fn render_literal(_oracle: &dyn CodeOracle, literal: &Literal) -> String {
         },
         Literal::Float(string, _type_) => string.clone(),
 
+        // `dictionary` fields can declare defaults for their compound and enum types; render each
+        // as the idiomatic Python literal so generated constructors carry them as keyword-argument
+        // defaults.
+        Literal::Null => "None".into(),
+        Literal::EmptySequence => "[]".into(),
+        Literal::EmptyMap => "{}".into(),
+        Literal::Enum(variant, type_) => format!(
+            "{}.{}",
+            oracle.find(type_).type_label(oracle),
+            oracle.enum_variant_name(variant),
+        ),
+
         _ => unreachable!("Literal"),
     }
 }
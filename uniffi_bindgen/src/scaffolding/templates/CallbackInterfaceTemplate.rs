@@ -0,0 +1,100 @@
+{#
+// For each callback interface declared in the UDL we generate a Rust proxy that implements the
+// corresponding trait by calling back across the FFI. The foreign side registers its
+// `ForeignCallback` vtable through the init function below (receiving a handle into the
+// `ForeignCallbackInternals` handle map), and the proxy forwards each trait method through
+// `invoke_callback`, which decodes the status-code/out-buffer outcome into a `Result`.
+//
+// Arguments are lowered and results/errors are lifted with the same `write`/`try_read` converters
+// the record scaffolding uses, so a declared `[Throws=E]` error enum round-trips across this path
+// exactly as any other serialized value would.
+#}
+#[doc(hidden)]
+static {{ cbi.name()|upper }}_INTERNALS: uniffi::ForeignCallbackInternals =
+    uniffi::ForeignCallbackInternals::new();
+
+#[doc(hidden)]
+#[no_mangle]
+pub extern "C" fn {{ cbi.ffi_init_callback().name() }}(callback: uniffi::ForeignCallback) -> u64 {
+    // Register the vtable and hand the foreign side back the handle it uses to refer to it.
+    {{ cbi.name()|upper }}_INTERNALS.register(callback)
+}
+
+#[doc(hidden)]
+struct {{ cbi.name() }}Proxy {
+    // Handle for the foreign object that implements the interface, looked up in the foreign
+    // language's own handle map.
+    handle: u64,
+    // Handle for the registered vtable in `{{ cbi.name()|upper }}_INTERNALS`.
+    callback_handle: u64,
+}
+
+impl ::std::ops::Drop for {{ cbi.name() }}Proxy {
+    fn drop(&mut self) {
+        // Tell the foreign side it can release the object (best effort; a dropping proxy must not
+        // panic, so we discard any error), then drop the vtable handle.
+        let _ = {{ cbi.name()|upper }}_INTERNALS
+            .invoke_callback::<(), uniffi::UnexpectedUniFFICallbackError>(
+                self.callback_handle,
+                self.handle,
+                uniffi::IDX_CALLBACK_FREE,
+                uniffi::RustBuffer::new(),
+                |_| (),
+                |buf| {
+                    let message = String::from_utf8_lossy(&buf.destroy_into_vec()).into_owned();
+                    uniffi::UnexpectedUniFFICallbackError::new(message)
+                },
+            );
+        {{ cbi.name()|upper }}_INTERNALS.unregister(self.callback_handle);
+    }
+}
+
+impl {{ cbi.name() }} for {{ cbi.name() }}Proxy {
+    {%- for meth in cbi.methods() %}
+    fn {{ meth.name() }}(&self, {% for arg in meth.arguments() %}{{ arg.name() }}: {{ arg.type_()|type_rs }}, {% endfor %}) -> {{ meth.return_type()|return_type_rs }} {
+        let mut buf = std::vec::Vec::new();
+        {%- for arg in meth.arguments() %}
+        {{ arg.type_()|ffi_converter }}::write({{ arg.name() }}, &mut buf);
+        {%- endfor %}
+        let args = uniffi::RustBuffer::from_vec(buf);
+        let extract_success = |mut buf: uniffi::RustBuffer| {
+            let buf = &mut buf.as_slice();
+            {%- match meth.return_type() %}
+            {%- when Some with (return_type) %}
+            {{ return_type|ffi_converter }}::try_read(buf)
+                .expect("Failed to read callback return value")
+            {%- when None %}
+            ()
+            {%- endmatch %}
+        };
+        {%- match meth.throws_type() %}
+        {%- when Some with (error_type) %}
+        // Declared `[Throws=E]`: success, a declared error, and any unexpected/stale-handle error
+        // (the latter via `E: From<UnexpectedUniFFICallbackError>`) all come back as a `Result`.
+        {{ cbi.name()|upper }}_INTERNALS.invoke_callback(
+            self.callback_handle,
+            self.handle,
+            {{ loop.index }},
+            args,
+            extract_success,
+            |mut buf| {
+                let buf = &mut buf.as_slice();
+                {{ error_type|ffi_converter }}::try_read(buf)
+                    .expect("Failed to read callback error value")
+            },
+        )
+        {%- when None %}
+        // Non-throwing: there is no error channel, so any non-success outcome is a contract
+        // violation and `invoke_callback_infallible` panics with the method name.
+        {{ cbi.name()|upper }}_INTERNALS.invoke_callback_infallible(
+            self.callback_handle,
+            self.handle,
+            {{ loop.index }},
+            "{{ meth.name() }}",
+            args,
+            extract_success,
+        )
+        {%- endmatch %}
+    }
+    {%- endfor %}
+}